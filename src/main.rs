@@ -32,6 +32,7 @@ use std::fs::File;
 use log::{info, warn};
 use xmltree::{Element, EmitterConfig};
 
+mod pac;
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!("\nUsage: {} [options] <input.edc> <output.svd>", program);
@@ -52,11 +53,108 @@ fn add_elem_with_text(parent: &mut Element, name: &str, text: &str){
     parent.children.push(elem);
 }
 
+// atomic write semantics of the xxxSET/xxxCLR/xxxINV portal registers, in
+// terms of the SVD <modifiedWriteValues> enumeration
+enum PortalKind {
+    Base,
+    Set,
+    Clear,
+    Invert,
+}
+
+impl PortalKind {
+    fn access(&self) -> &'static str {
+        match self {
+            PortalKind::Base => "read-write",
+            PortalKind::Set | PortalKind::Clear | PortalKind::Invert => "write-only",
+        }
+    }
+
+    fn modified_write_values(&self) -> Option<&'static str> {
+        match self {
+            PortalKind::Base => None,
+            PortalKind::Set => Some("oneToSet"),
+            PortalKind::Clear => Some("oneToClear"),
+            PortalKind::Invert => Some("oneToToggle"),
+        }
+    }
+}
+
+// maps an EDC field access attribute to the SVD <access> value; fields
+// without a recognised attribute default to the register's own access,
+// i.e. no <access> element is emitted for them
+fn field_access(attr: &xmltree::Element) -> Option<&'static str> {
+    match attr.attributes.get("access").map(|s| s.as_str()) {
+        Some("read-only") | Some("r") => Some("read-only"),
+        Some("write-only") | Some("w") => Some("write-only"),
+        _ => None,
+    }
+}
+
+// collects the documented bit encodings of a field from its SFRFieldSemantic
+// children, dropping (and warning about) any entry whose numeric value
+// collides with one already seen
+fn collect_enumerated_values(field_def_e: &Element, fname: &str) -> Vec<(u32, String, String)> {
+    let mut values = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for semantic_e in field_def_e.children.iter() {
+        if semantic_e.name != "SFRFieldSemantic" {
+            continue;
+        }
+        let value = match semantic_e.attributes.get("value").and_then(|v| parse_u32(v).ok()) {
+            Some(v) => v,
+            None => continue,
+        };
+        if !seen.insert(value) {
+            warn!("field {}: duplicate enumerated value {}; keeping first", fname, value);
+            continue;
+        }
+        let ev_name = semantic_e
+            .attributes
+            .get("cname")
+            .or_else(|| semantic_e.attributes.get("name"))
+            .cloned()
+            .unwrap_or_else(|| format!("VAL_{}", value));
+        let desc = semantic_e
+            .attributes
+            .get("desc")
+            .cloned()
+            .unwrap_or_else(|| ev_name.clone());
+        values.push((value, ev_name, desc));
+    }
+    values
+}
+
+// builds the <enumeratedValues> block for a field, adding a catch-all
+// `isDefault` entry when only a subset of the possible encodings is
+// documented in the EDC file
+fn enumerated_values_elem(values: &[(u32, String, String)], width: u32) -> Element {
+    let mut enumvals_e = Element::new("enumeratedValues");
+    for (value, ev_name, desc) in values {
+        let mut ev_e = Element::new("enumeratedValue");
+        add_elem_with_text(&mut ev_e, "name", ev_name);
+        add_elem_with_text(&mut ev_e, "description", desc);
+        add_elem_with_text(&mut ev_e, "value", &format!("{}", value));
+        enumvals_e.children.push(ev_e);
+    }
+    let possible_encodings: u64 = 1u64 << width;
+    if (values.len() as u64) < possible_encodings {
+        let mut ev_e = Element::new("enumeratedValue");
+        add_elem_with_text(&mut ev_e, "name", "RESERVED");
+        add_elem_with_text(&mut ev_e, "description", "reserved or undocumented encoding");
+        add_elem_with_text(&mut ev_e, "isDefault", "true");
+        enumvals_e.children.push(ev_e);
+    }
+    enumvals_e
+}
+
 fn add_register(peri_out_e: &mut Element,
                 name: &str,
                 offset: u32,
+                reg_width: u32,
                 reset_val: u32,
-                sfrmode_e: &Element)
+                sfrmode_e: &Element,
+                portal: PortalKind)
 {
     let peri_e = &mut peri_out_e.children.last_mut().unwrap();
     let registers = &mut peri_e.children.last_mut().unwrap();
@@ -65,8 +163,12 @@ fn add_register(peri_out_e: &mut Element,
     add_elem_with_text(&mut reg_e, "name", name);
     add_elem_with_text(&mut reg_e, "description", &format!("{} register", name));
     add_elem_with_text(&mut reg_e, "addressOffset", &format!("0x{:0x}", offset));
-    add_elem_with_text(&mut reg_e, "size", "32");
+    add_elem_with_text(&mut reg_e, "size", &format!("{}", reg_width));
+    add_elem_with_text(&mut reg_e, "access", portal.access());
     add_elem_with_text(&mut reg_e, "resetValue", &format!("{}", reset_val));
+    if let Some(mwv) = portal.modified_write_values() {
+        add_elem_with_text(&mut reg_e, "modifiedWriteValues", mwv);
+    }
 
     // add field descriptions if any
     let mut fields_e = Element::new("fields");
@@ -84,6 +186,21 @@ fn add_register(peri_out_e: &mut Element,
             add_elem_with_text(&mut field_e,
                                "bitRange",
                                &format!("[{}:{}]", bitpos + width - 1, bitpos));
+            // the EDC field access only describes the base register; the
+            // CLR/SET/INV portals are write-only registers in their own
+            // right (see PortalKind::access), so re-emitting e.g. a
+            // read-only field access on them would contradict the register
+            // they belong to. Leave those fields without an <access>
+            // override so they inherit the portal register's own access.
+            if let PortalKind::Base = portal {
+                if let Some(access) = field_access(elem) {
+                    add_elem_with_text(&mut field_e, "access", access);
+                }
+            }
+            let enum_values = collect_enumerated_values(elem, fname);
+            if !enum_values.is_empty() {
+                field_e.children.push(enumerated_values_elem(&enum_values, width));
+            }
             fields_e.children.push(field_e);
             bitpos += width;
         }else if elem.name == "AdjustPoint" {
@@ -99,6 +216,107 @@ fn add_register(peri_out_e: &mut Element,
     registers.children.push(reg_e);
 }
 
+// guesses the owning peripheral name from the attributes of an EDC element
+// (an SFRDef or an Interrupt), in decreasing order of specificity; returns
+// None when no attribute gives any clue, and Some("") when an attribute was
+// present but did not resolve to a known peripheral (e.g. an unrecognized
+// _modsrc value)
+fn guess_peripheral_name(attr: &std::collections::HashMap<String, String>) -> Option<String> {
+    let mop = match attr.get("memberofperipheral") {
+        Some(m) => if m.len() == 0 { None } else { Some(m) },
+        None => None,
+    };
+    let mut cperi: String;
+    if let Some(bop) = attr.get("baseofperipheral") {
+        cperi = bop.clone();
+    } else if let Some(m) = mop {
+        cperi = m.clone();
+    } else if let Some(grp) = attr.get("grp") {
+        cperi = grp.clone();
+    } else if let Some(ms) = attr.get("_modsrc") {
+        cperi = if ms == "DOS-01618_RPINRx.Module" ||
+                   ms == "DOS-01618_RPORx.Module"  ||
+                   ms == "DOS-01423_RPINRx.Module" ||
+                   ms == "DOS-01423_RPORx.Module"
+        {
+            String::from("PPS")
+        }else if ms == "DOS-01475_lpwr_deep_sleep_ctrl_v2.Module" {
+            String::from("DSCTRL") // Deep Sleep Controller
+        } else {
+            String::from("")
+        };
+    } else {
+        return None;
+    }
+    let words: Vec<&str> = cperi.split(' ').collect();
+    if let Some(word) = words.get(0) {
+        cperi = word.to_string();
+    }
+    // cperi may be empty here (e.g. an unrecognized _modsrc value); the
+    // caller distinguishes that from "no attribute at all" (None) since the
+    // two call for different diagnostics
+    Some(cperi)
+}
+
+// attaches <interrupt> children to the <peripheral> elements in
+// `periph_out_e` that own them, as declared by the EDC interrupt/vector
+// list; interrupts that cannot be attributed to any emitted peripheral are
+// warned about and left out rather than silently dropped
+fn analyze_interrupts(docelem: &Element, periph_out_e: &mut Element) {
+    let interrupt_list_e = match docelem.get_child("InterruptList") {
+        Some(e) => e,
+        None => {
+            warn!("InterruptList element missing; no interrupts attached to peripherals");
+            return;
+        }
+    };
+    for irq_e in interrupt_list_e.children.iter() {
+        if irq_e.name != "Interrupt" {
+            continue;
+        }
+        let attr = &irq_e.attributes;
+        let irq_name = match attr.get("cname").or_else(|| attr.get("name")) {
+            Some(n) => n,
+            None => {
+                warn!("Interrupt element without a name attribute; skipping");
+                continue;
+            }
+        };
+        let vector = match attr.get("irq").and_then(|v| parse_u32(v).ok()) {
+            Some(v) => v,
+            None => {
+                warn!("interrupt {}: missing or unparsable irq/vector number; skipping", irq_name);
+                continue;
+            }
+        };
+        let cperi = match guess_peripheral_name(attr) {
+            Some(p) if !p.is_empty() => p,
+            _ => {
+                warn!("interrupt {}: cannot attribute to any peripheral; skipping", irq_name);
+                continue;
+            }
+        };
+        let peri_e = periph_out_e.children.iter_mut().find(|e| {
+            e.name == "peripheral" && elem_text(e, "name") == cperi
+        });
+        match peri_e {
+            Some(peri_e) => {
+                let mut interrupt_e = Element::new("interrupt");
+                add_elem_with_text(&mut interrupt_e, "name", irq_name);
+                add_elem_with_text(&mut interrupt_e, "value", &format!("{}", vector));
+                // <interrupt> must precede <registers> per the CMSIS-SVD schema
+                let pos = peri_e
+                    .children
+                    .iter()
+                    .position(|c| c.name == "registers")
+                    .unwrap_or(peri_e.children.len());
+                peri_e.children.insert(pos, interrupt_e);
+            }
+            None => warn!("interrupt {}: peripheral {} not found; skipping", irq_name, cperi),
+        }
+    }
+}
+
 fn analyze_periph(periph: &Element, periph_out_e: &mut Element) {
     let mut peri = String::new();
     let mut base_addr: u32 = 0;
@@ -123,6 +341,16 @@ fn analyze_periph(periph: &Element, periph_out_e: &mut Element) {
                 _ => panic!(format!("unexpected portals attribute: {}", portals)),
             };
 
+            // EDC describes 8-, 16- and 32-bit SFRs; default to a full
+            // 32-bit word when the width is not given explicitly. nzwidth
+            // is the implemented field width, not the portal stride: the
+            // CLR/SET/INV atomic bit-manipulation aliases always sit a
+            // fixed 32-bit word away from the base register regardless of
+            // how many bits of that word are actually implemented.
+            let reg_width = attr.get("nzwidth")
+                .and_then(|w| parse_u32(w).ok())
+                .unwrap_or(32);
+
             // get reset value; map unimplemented (-) or undefined (x) bits to 0
             let reset_str = attr["mclr"]
                 .replace("-", "0")
@@ -133,36 +361,8 @@ fn analyze_periph(periph: &Element, periph_out_e: &mut Element) {
             });
 
             // guess peripheral
-            let mop = match attr.get("memberofperipheral") {
-                Some(m) => if m.len() == 0 { None } else { Some(m) },
-                None => None,
-            };
-            let mut cperi: String;
-            if let Some(bop) = attr.get("baseofperipheral") {
-                cperi = bop.clone();
-            } else if let Some(m) = mop {
-                cperi = m.clone();
-            } else if let Some(grp) = attr.get("grp") {
-                cperi = grp.clone();
-            } else if let Some(ms) = attr.get("_modsrc") {
-                cperi = if ms == "DOS-01618_RPINRx.Module" ||
-                           ms == "DOS-01618_RPORx.Module"  ||
-                           ms == "DOS-01423_RPINRx.Module" ||
-                           ms == "DOS-01423_RPORx.Module"
-                {
-                    String::from("PPS")
-                }else if ms == "DOS-01475_lpwr_deep_sleep_ctrl_v2.Module" {
-                    String::from("DSCTRL") // Deep Sleep Controller
-                } else {
-                    String::from("")
-                };
-            } else {
-                panic!(format!("missing peripheral for {}", name));
-            }
-            let words: Vec<&str> = cperi.split(' ').collect();
-            if let Some(word) = words.get(0) {
-                cperi = word.to_string();
-            }
+            let cperi = guess_peripheral_name(attr)
+                .unwrap_or_else(|| panic!("missing peripheral for {}", name));
             if cperi.len() == 0 {
                 panic!(format!("empty peripheral info for {}", name));
             }
@@ -197,31 +397,280 @@ fn analyze_periph(periph: &Element, periph_out_e: &mut Element) {
                    name,
                    addr, offset, reset,
                    portals);
-            add_register(periph_out_e, name, offset, reset, sfrmode_e);
+            add_register(periph_out_e, name, offset, reg_width, reset, sfrmode_e, PortalKind::Base);
             if clr {
                 info!("\t{}CLR: {:0x}, offset = {:0x}",
                     name,
                     addr + 0x4, offset + 0x04);
                 // use 0 as reset value; read from this register is undefined
-                add_register(periph_out_e, &format!("{}CLR", name), offset + 4, 0, sfrmode_e);
+                add_register(periph_out_e, &format!("{}CLR", name), offset + 4, reg_width, 0, sfrmode_e, PortalKind::Clear);
             }
             if set {
                 info!("\t{}SET: {:0x}, offset = {:0x}",
                     name,
                     addr + 0x8, offset + 8);
                 // use 0 as reset value; read from this register is undefined
-                add_register(periph_out_e, &format!("{}SET", name), offset + 8, 0, sfrmode_e);
+                add_register(periph_out_e, &format!("{}SET", name), offset + 8, reg_width, 0, sfrmode_e, PortalKind::Set);
             }
             if inv {
                 info!("\t{}INV: {:0x}, offset = {:0x}",
                     name,
                     addr + 0xc, offset + 0xc);
                 // use 0 as reset value; read from this register is undefined
-                add_register(periph_out_e, &format!("{}INV", name), offset + 0xc, 0, sfrmode_e);
+                add_register(periph_out_e, &format!("{}INV", name), offset + 0xc, reg_width, 0, sfrmode_e, PortalKind::Invert);
             }
             info!("");
         }
     }
+    merge_narrow_registers(periph_out_e);
+}
+
+fn reg_offset(reg_e: &Element) -> u32 {
+    parse_u32(elem_text(reg_e, "addressOffset")).unwrap()
+}
+
+fn reg_size(reg_e: &Element) -> u32 {
+    parse_u32(elem_text(reg_e, "size")).unwrap()
+}
+
+// narrow (8/16-bit) registers that fall into the same 32-bit aligned word
+// either describe a genuine hardware byte/word alias (several named SFRDefs
+// documented at the identical address, e.g. a byte view and a word view of
+// the same storage) or a real modeling conflict (different, partially
+// overlapping addresses); the former is expressed in SVD via
+// <alternateRegister> without touching either address, and the latter
+// cannot be resolved by inventing a synthetic address without
+// misrepresenting the silicon, so it is only flagged for manual review
+fn merge_narrow_registers(periph_out_e: &mut Element) {
+    for peri_e in periph_out_e.children.iter_mut() {
+        if peri_e.name != "peripheral" {
+            continue;
+        }
+        let registers_e = match peri_e.get_mut_child("registers") {
+            Some(r) => r,
+            None => continue,
+        };
+
+        let mut words: std::collections::BTreeMap<u32, Vec<usize>> = std::collections::BTreeMap::new();
+        for (i, reg_e) in registers_e.children.iter().enumerate() {
+            if reg_e.name != "register" || reg_size(reg_e) >= 32 {
+                continue;
+            }
+            words.entry(reg_offset(reg_e) & !0x3).or_insert_with(Vec::new).push(i);
+        }
+
+        for (word, idxs) in words {
+            if idxs.len() < 2 {
+                continue;
+            }
+            // (byte lane, size in bytes, register index), relative to `word`
+            let mut occupied: Vec<(u32, u32, usize)> = Vec::new();
+            for i in idxs {
+                let size_bytes = (reg_size(&registers_e.children[i]) + 7) / 8;
+                let rel = reg_offset(&registers_e.children[i]) - word;
+                if let Some(&(_, _, first)) = occupied.iter().find(|&(o, _, _)| *o == rel) {
+                    let first_name = elem_text(&registers_e.children[first], "name").to_string();
+                    let this_name = elem_text(&registers_e.children[i], "name").to_string();
+                    info!("register {} aliases {} at word 0x{:x}", this_name, first_name, word);
+                    let reg_e = &mut registers_e.children[i];
+                    if reg_e.get_child("alternateRegister").is_none() {
+                        let mut alt_e = Element::new("alternateRegister");
+                        alt_e.text = Some(first_name);
+                        // <alternateRegister> must precede <addressOffset> per the CMSIS-SVD schema
+                        let pos = reg_e
+                            .children
+                            .iter()
+                            .position(|c| c.name == "addressOffset")
+                            .unwrap_or(reg_e.children.len());
+                        reg_e.children.insert(pos, alt_e);
+                    }
+                } else if occupied
+                    .iter()
+                    .any(|&(o, s, _)| rel < o + s && o < rel + size_bytes)
+                {
+                    warn!("register {} at 0x{:x} partially overlaps another register in word 0x{:x}; \
+                           addresses left unchanged, please check the source EDC file",
+                          elem_text(&registers_e.children[i], "name"), word + rel, word);
+                }
+                occupied.push((rel, size_bytes, i));
+            }
+        }
+    }
+}
+
+// the part of the peripheral's own name that varies from instance to
+// instance: a trailing run of digits (U1, U2, ...) or, failing that, a
+// single trailing instance letter (PORTA, PORTB, ...)
+fn instance_token(peri_name: &str) -> String {
+    let digit_count = peri_name.chars().rev().take_while(|c| c.is_ascii_digit()).count();
+    if digit_count > 0 {
+        return peri_name[peri_name.len() - digit_count..].to_string();
+    }
+    match peri_name.chars().last() {
+        Some(c) if c.is_ascii_alphabetic() && peri_name.len() > 1 => c.to_string(),
+        _ => String::new(),
+    }
+}
+
+// strips the instance-specific part of a register name that corresponds to
+// the peripheral's own name, e.g. normalize_register_name("U1", "U1MODE")
+// == "MODE", so that structurally identical instances (UART1..UART6,
+// PORTA..PORTK, ...) fingerprint the same regardless of their number/letter
+fn normalize_register_name(peri_name: &str, reg_name: &str) -> String {
+    if let Some(stripped) = reg_name.strip_prefix(peri_name) {
+        return stripped.to_string();
+    }
+    let token = instance_token(peri_name);
+    if !token.is_empty() {
+        let prefix_no_token = &peri_name[..peri_name.len() - token.len()];
+        // prefixed instance, e.g. peripheral "U1", register "U1MODE" -> "MODE"
+        if let Some(rest) = reg_name.strip_prefix(prefix_no_token) {
+            if let Some(rest) = rest.strip_prefix(token.as_str()) {
+                return rest.to_string();
+            }
+        }
+        // suffixed instance, e.g. peripheral "PORTA", register "TRISA" -> "TRIS"
+        if let Some(rest) = reg_name.strip_suffix(token.as_str()) {
+            return rest.to_string();
+        }
+    }
+    reg_name.to_string()
+}
+
+fn elem_text<'a>(parent: &'a Element, name: &str) -> &'a str {
+    parent
+        .get_child(name)
+        .and_then(|e| e.text.as_deref())
+        .unwrap_or("")
+}
+
+// structural fingerprint of a peripheral's register/field tree, used to
+// detect peripherals that are mere repeated instances of each other; this
+// includes each field's access and enumerated encodings so that instances
+// differing only in those (not just name/bitRange) don't get collapsed
+fn fingerprint_peripheral(peri_name: &str, peri_e: &Element) -> String {
+    let registers_e = match peri_e.get_child("registers") {
+        Some(r) => r,
+        None => return String::new(),
+    };
+    let mut parts = Vec::new();
+    for reg_e in registers_e.children.iter().filter(|e| e.name == "register") {
+        let suffix = normalize_register_name(peri_name, elem_text(reg_e, "name"));
+        let mut fields = String::new();
+        if let Some(fields_e) = reg_e.get_child("fields") {
+            for field_e in fields_e.children.iter().filter(|e| e.name == "field") {
+                let mut enum_values = String::new();
+                if let Some(enumvals_e) = field_e.get_child("enumeratedValues") {
+                    for ev_e in enumvals_e.children.iter().filter(|e| e.name == "enumeratedValue") {
+                        enum_values.push_str(&format!(
+                            "{}={},",
+                            elem_text(ev_e, "value"),
+                            elem_text(ev_e, "name")
+                        ));
+                    }
+                }
+                fields.push_str(&format!(
+                    "{}:{}:{}:{};",
+                    elem_text(field_e, "name"),
+                    elem_text(field_e, "bitRange"),
+                    elem_text(field_e, "access"),
+                    enum_values
+                ));
+            }
+        }
+        parts.push(format!(
+            "{}|{}|{}|{}|{}",
+            suffix,
+            elem_text(reg_e, "addressOffset"),
+            elem_text(reg_e, "size"),
+            elem_text(reg_e, "resetValue"),
+            fields
+        ));
+    }
+    parts.join("\n")
+}
+
+// replaces peripherals that are structurally identical to an earlier one
+// with a `derivedFrom` reference, as CMSIS-SVD does for repeated instances
+// such as UART1..UART6 or PORTA..PORTK
+fn dedup_peripherals(periph_out: &mut Element) {
+    let mut seen: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+    for peri_e in periph_out.children.iter_mut() {
+        if peri_e.name != "peripheral" || peri_e.get_child("registers").is_none() {
+            continue;
+        }
+        let peri_name = elem_text(peri_e, "name").to_string();
+        let fingerprint = fingerprint_peripheral(&peri_name, peri_e);
+        if let Some(first) = seen.get(&fingerprint) {
+            info!("{} is structurally identical to {}", peri_name, first);
+            peri_e
+                .attributes
+                .insert("derivedFrom".to_string(), first.clone());
+            peri_e.children.retain(|c| {
+                c.name == "name" || c.name == "description" || c.name == "baseAddress" ||
+                c.name == "interrupt"
+            });
+        } else {
+            seen.insert(fingerprint, peri_name);
+        }
+    }
+}
+
+// builds the <cpu> block; PIC32 parts use a MIPS core, which has no entry
+// in the CMSIS-SVD cpu-name enumeration, so "other" is the correct value
+fn build_cpu_elem(docelem: &Element) -> Element {
+    let mut cpu_e = Element::new("cpu");
+    add_elem_with_text(&mut cpu_e, "name", "other");
+    add_elem_with_text(&mut cpu_e, "revision",
+                        docelem.attributes.get("procid").map(String::as_str).unwrap_or("r1p0"));
+    let endian = docelem.attributes.get("endian").map(String::as_str).unwrap_or("little");
+    add_elem_with_text(&mut cpu_e, "endian", endian);
+    add_elem_with_text(&mut cpu_e, "mpuPresent", "false");
+    add_elem_with_text(&mut cpu_e, "fpuPresent", "false");
+    add_elem_with_text(&mut cpu_e, "nvicPrioBits", "0");
+    add_elem_with_text(&mut cpu_e, "vendorSystickConfig", "false");
+    cpu_e
+}
+
+// emits a memory region (flash, RAM, ...) as a register-less peripheral with
+// a <addressBlock usage="memory">, the idiomatic CMSIS-SVD way of describing
+// a plain address range that is not a set of registers
+fn add_memory_region(periph_out_e: &mut Element, name: &str, description: &str, base: u32, size: u32) {
+    let mut peri_e = Element::new("peripheral");
+    add_elem_with_text(&mut peri_e, "name", name);
+    add_elem_with_text(&mut peri_e, "description", description);
+    add_elem_with_text(&mut peri_e, "baseAddress", &format!("0x{:08x}", base));
+    let mut addr_block_e = Element::new("addressBlock");
+    add_elem_with_text(&mut addr_block_e, "offset", "0x0");
+    add_elem_with_text(&mut addr_block_e, "size", &format!("0x{:x}", size));
+    add_elem_with_text(&mut addr_block_e, "usage", "memory");
+    peri_e.children.push(addr_block_e);
+    periph_out_e.children.push(peri_e);
+}
+
+// scans an EDC memory-space element (ProgramSpace, DataSpace, ...) for its
+// address range and, if found, appends it to `periph_out_e` as a memory
+// region; warns instead of panicking since this is best-effort metadata
+fn add_memory_region_from_space(periph_out_e: &mut Element, docelem: &Element,
+                                 space_name: &str, region_name: &str, description: &str) {
+    let space_e = match docelem.get_child(space_name) {
+        Some(e) => e,
+        None => {
+            warn!("{} element missing; skipping {} memory region", space_name, region_name);
+            return;
+        }
+    };
+    let begin = space_e.attributes.get("beginaddr").and_then(|a| parse_u32(a).ok());
+    let end = space_e.attributes.get("endaddr").and_then(|a| parse_u32(a).ok());
+    match (begin, end) {
+        (Some(base), Some(limit)) if limit >= base => {
+            // endaddr is the last valid (inclusive) address in EDC's
+            // memory-space descriptors, not an exclusive limit
+            add_memory_region(periph_out_e, region_name, description, base, limit - base + 1);
+        }
+        _ => warn!("{} element missing beginaddr/endaddr; skipping {} memory region",
+                    space_name, region_name),
+    }
 }
 
 fn setup_logger(loglevel: log::LevelFilter) {
@@ -239,6 +688,7 @@ fn main() {
     let mut opts = Options::new();
     opts.optflag("h", "help", "show this help message");
     opts.optflag("v", "verbose", "activate verbose output");
+    opts.optopt("f", "format", "output format: svd (default) or rust", "FORMAT");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -258,6 +708,7 @@ fn main() {
         return;
     }
     let (edcfn, svdfn) = (&matches.free[0], &matches.free[1]);
+    let format = matches.opt_str("f").unwrap_or_else(|| String::from("svd"));
 
     let infile = File::open(&edcfn)
         .expect(&format!("cannot open file {}", edcfn));
@@ -268,20 +719,232 @@ fn main() {
         .expect("PhysicalSpace element missing");
 
     let mut develem = Element::new("device");
-    let mut name_e = Element::new("name");
-    name_e.text = Some(name.to_string());
-    develem.children.push(name_e);
+    add_elem_with_text(&mut develem, "name", name);
+    add_elem_with_text(&mut develem, "version", "1.0");
+    let description = docelem.attributes.get("desc").cloned()
+        .unwrap_or_else(|| format!("{} microcontroller", name));
+    add_elem_with_text(&mut develem, "description", &description);
+    // CMSIS-SVD's xs:sequence requires <cpu> immediately after
+    // version/description and before addressUnitBits
+    develem.children.push(build_cpu_elem(&docelem));
+    add_elem_with_text(&mut develem, "addressUnitBits", "8");
+    add_elem_with_text(&mut develem, "width", "32");
+    add_elem_with_text(&mut develem, "size", "32");
+    add_elem_with_text(&mut develem, "resetValue", "0x00000000");
+    add_elem_with_text(&mut develem, "resetMask", "0xFFFFFFFF");
     let mut periph_out = Element::new("peripherals");
 
     for child in phys.children.iter() {
-        if child.name == "SFRDataSector" && 
+        if child.name == "SFRDataSector" &&
            child.attributes.get("regionid").unwrap_or(&String::from("")).starts_with("periph")
         {
             analyze_periph(child, &mut periph_out);
         }
     }
-    let outfile = File::create(&svdfn).expect(&format!("cannot open file {}", svdfn));
-    let config = EmitterConfig::new().perform_indent(true);
-    develem.children.push(periph_out);
-    develem.write_with_config(outfile, config).unwrap();
+    add_memory_region_from_space(&mut periph_out, &docelem, "ProgramSpace", "FLASH", "Program Flash Memory");
+    add_memory_region_from_space(&mut periph_out, &docelem, "DataSpace", "RAM", "Data RAM");
+    analyze_interrupts(&docelem, &mut periph_out);
+
+    match format.as_str() {
+        "svd" => {
+            dedup_peripherals(&mut periph_out);
+            let outfile = File::create(&svdfn).expect(&format!("cannot open file {}", svdfn));
+            let config = EmitterConfig::new().perform_indent(true);
+            develem.children.push(periph_out);
+            develem.write_with_config(outfile, config).unwrap();
+        }
+        "rust" => {
+            let code = pac::generate_pac(name, &periph_out);
+            std::fs::write(&svdfn, code).expect(&format!("cannot write file {}", svdfn));
+        }
+        _ => panic!(format!("unknown output format: {}", format)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_token_digit_suffix() {
+        assert_eq!(instance_token("U1"), "1");
+        assert_eq!(instance_token("UART12"), "12");
+    }
+
+    #[test]
+    fn normalize_register_name_multi_digit_instance() {
+        assert_eq!(normalize_register_name("UART12", "UART12MODE"), "MODE");
+    }
+
+    #[test]
+    fn instance_token_letter_suffix() {
+        assert_eq!(instance_token("PORTA"), "A");
+    }
+
+    #[test]
+    fn instance_token_single_char_name_has_no_token() {
+        assert_eq!(instance_token("X"), "");
+    }
+
+    #[test]
+    fn normalize_register_name_digit_instance() {
+        assert_eq!(normalize_register_name("U1", "U1MODE"), "MODE");
+    }
+
+    #[test]
+    fn normalize_register_name_letter_instance() {
+        assert_eq!(normalize_register_name("PORTA", "TRISA"), "TRIS");
+        assert_eq!(normalize_register_name("PORTA", "LATA"), "LAT");
+    }
+
+    #[test]
+    fn normalize_register_name_unrelated() {
+        assert_eq!(normalize_register_name("SPI1", "IFS0"), "IFS0");
+    }
+
+    fn register_elem(name: &str, offset: &str, size: &str, reset: &str) -> Element {
+        let mut reg_e = Element::new("register");
+        add_elem_with_text(&mut reg_e, "name", name);
+        add_elem_with_text(&mut reg_e, "addressOffset", offset);
+        add_elem_with_text(&mut reg_e, "size", size);
+        add_elem_with_text(&mut reg_e, "resetValue", reset);
+        reg_e
+    }
+
+    fn peripheral_elem(registers: Vec<Element>) -> Element {
+        let mut peri_e = Element::new("peripheral");
+        let mut registers_e = Element::new("registers");
+        registers_e.children = registers;
+        peri_e.children.push(registers_e);
+        peri_e
+    }
+
+    #[test]
+    fn fingerprint_peripheral_matches_across_instances() {
+        let u1 = peripheral_elem(vec![register_elem("U1MODE", "0x0", "32", "0")]);
+        let u2 = peripheral_elem(vec![register_elem("U2MODE", "0x0", "32", "0")]);
+        assert_eq!(fingerprint_peripheral("U1", &u1), fingerprint_peripheral("U2", &u2));
+    }
+
+    #[test]
+    fn fingerprint_peripheral_differs_on_reset_value() {
+        let a = peripheral_elem(vec![register_elem("U1MODE", "0x0", "32", "0")]);
+        let b = peripheral_elem(vec![register_elem("U2MODE", "0x0", "32", "1")]);
+        assert_ne!(fingerprint_peripheral("U1", &a), fingerprint_peripheral("U2", &b));
+    }
+
+    fn semantic_elem(value: &str, cname: &str, desc: &str) -> Element {
+        let mut e = Element::new("SFRFieldSemantic");
+        e.attributes.insert("value".to_string(), value.to_string());
+        e.attributes.insert("cname".to_string(), cname.to_string());
+        e.attributes.insert("desc".to_string(), desc.to_string());
+        e
+    }
+
+    #[test]
+    fn collect_enumerated_values_collects_each_semantic() {
+        let mut field_def_e = Element::new("SFRFieldDef");
+        field_def_e.children.push(semantic_elem("0x0", "OFF", "disabled"));
+        field_def_e.children.push(semantic_elem("0x1", "ON", "enabled"));
+        let values = collect_enumerated_values(&field_def_e, "MODE");
+        assert_eq!(
+            values,
+            vec![
+                (0, "OFF".to_string(), "disabled".to_string()),
+                (1, "ON".to_string(), "enabled".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn collect_enumerated_values_drops_duplicate_value() {
+        let mut field_def_e = Element::new("SFRFieldDef");
+        field_def_e.children.push(semantic_elem("0x0", "OFF", "disabled"));
+        field_def_e.children.push(semantic_elem("0x0", "DISABLED", "also disabled"));
+        let values = collect_enumerated_values(&field_def_e, "MODE");
+        assert_eq!(values, vec![(0, "OFF".to_string(), "disabled".to_string())]);
+    }
+
+    #[test]
+    fn enumerated_values_elem_adds_reserved_when_incomplete() {
+        let values = vec![(0, "OFF".to_string(), "disabled".to_string())];
+        let enumvals_e = enumerated_values_elem(&values, 1);
+        assert_eq!(enumvals_e.children.len(), 2);
+        assert_eq!(elem_text(enumvals_e.children.last().unwrap(), "name"), "RESERVED");
+    }
+
+    #[test]
+    fn enumerated_values_elem_no_reserved_when_complete() {
+        let values = vec![
+            (0, "OFF".to_string(), "disabled".to_string()),
+            (1, "ON".to_string(), "enabled".to_string()),
+        ];
+        let enumvals_e = enumerated_values_elem(&values, 1);
+        assert_eq!(enumvals_e.children.len(), 2);
+        assert_eq!(elem_text(enumvals_e.children.last().unwrap(), "name"), "ON");
+    }
+
+    fn narrow_register_elem(name: &str, offset: &str, size: &str) -> Element {
+        let mut reg_e = Element::new("register");
+        add_elem_with_text(&mut reg_e, "name", name);
+        add_elem_with_text(&mut reg_e, "addressOffset", offset);
+        add_elem_with_text(&mut reg_e, "size", size);
+        reg_e
+    }
+
+    fn periph_out_with_registers(registers: Vec<Element>) -> Element {
+        let mut periph_out_e = Element::new("peripherals");
+        let mut peri_e = Element::new("peripheral");
+        let mut registers_e = Element::new("registers");
+        registers_e.children = registers;
+        peri_e.children.push(registers_e);
+        periph_out_e.children.push(peri_e);
+        periph_out_e
+    }
+
+    #[test]
+    fn merge_narrow_registers_marks_same_address_as_alternate() {
+        let mut periph_out_e = periph_out_with_registers(vec![
+            narrow_register_elem("PORTAbyte", "0x0", "8"),
+            narrow_register_elem("PORTAalias", "0x0", "8"),
+        ]);
+        merge_narrow_registers(&mut periph_out_e);
+        let registers_e = periph_out_e.children[0].get_child("registers").unwrap();
+        assert!(registers_e.children[0].get_child("alternateRegister").is_none());
+        assert_eq!(
+            registers_e.children[1]
+                .get_child("alternateRegister")
+                .and_then(|e| e.text.as_deref()),
+            Some("PORTAbyte")
+        );
+        assert_eq!(elem_text(&registers_e.children[1], "addressOffset"), "0x0");
+    }
+
+    #[test]
+    fn merge_narrow_registers_leaves_non_overlapping_registers_untouched() {
+        let mut periph_out_e = periph_out_with_registers(vec![
+            narrow_register_elem("LO", "0x0", "16"),
+            narrow_register_elem("HI", "0x2", "16"),
+        ]);
+        merge_narrow_registers(&mut periph_out_e);
+        let registers_e = periph_out_e.children[0].get_child("registers").unwrap();
+        assert!(registers_e.children[0].get_child("alternateRegister").is_none());
+        assert!(registers_e.children[1].get_child("alternateRegister").is_none());
+        assert_eq!(elem_text(&registers_e.children[0], "addressOffset"), "0x0");
+        assert_eq!(elem_text(&registers_e.children[1], "addressOffset"), "0x2");
+    }
+
+    #[test]
+    fn merge_narrow_registers_leaves_partial_overlap_addresses_unchanged() {
+        let mut periph_out_e = periph_out_with_registers(vec![
+            narrow_register_elem("A", "0x0", "16"),
+            narrow_register_elem("B", "0x1", "16"),
+        ]);
+        merge_narrow_registers(&mut periph_out_e);
+        let registers_e = periph_out_e.children[0].get_child("registers").unwrap();
+        assert!(registers_e.children[0].get_child("alternateRegister").is_none());
+        assert!(registers_e.children[1].get_child("alternateRegister").is_none());
+        assert_eq!(elem_text(&registers_e.children[0], "addressOffset"), "0x0");
+        assert_eq!(elem_text(&registers_e.children[1], "addressOffset"), "0x1");
+    }
 }
@@ -0,0 +1,277 @@
+//
+// Rust peripheral-access crate (PAC) generator
+//
+// Alternative to the SVD backend: walks the same in-memory
+// peripheral/register/field element tree built by `analyze_periph` and
+// emits a `no_std` Rust module exposing each peripheral as a zero-sized
+// struct with volatile accessors, so PIC32 users can skip the separate
+// svd2rust step.
+//
+
+use xmltree::Element;
+
+struct Field {
+    name: String,
+    lsb: u32,
+    msb: u32,
+}
+
+fn child_text<'a>(elem: &'a Element, name: &str) -> &'a str {
+    elem.get_child(name)
+        .and_then(|e| e.text.as_deref())
+        .unwrap_or_else(|| panic!("missing <{}> element", name))
+}
+
+fn parse_hex_or_dec(text: &str) -> u32 {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).unwrap(),
+        None => text.parse().unwrap(),
+    }
+}
+
+fn parse_fields(reg_e: &Element) -> Vec<Field> {
+    let mut fields = Vec::new();
+    if let Some(fields_e) = reg_e.get_child("fields") {
+        for field_e in fields_e.children.iter().filter(|e| e.name == "field") {
+            let name = child_text(field_e, "name").to_string();
+            let bit_range = child_text(field_e, "bitRange");
+            let trimmed = bit_range.trim_start_matches('[').trim_end_matches(']');
+            let mut parts = trimmed.split(':');
+            let msb: u32 = parts.next().unwrap().parse().unwrap();
+            let lsb: u32 = parts.next().unwrap().parse().unwrap();
+            fields.push(Field { name, lsb, msb });
+        }
+    }
+    fields
+}
+
+fn gen_register_newtype(type_name: &str, fields: &[Field]) -> String {
+    let mut out = String::new();
+    out.push_str("#[derive(Clone, Copy)]\n#[repr(transparent)]\n");
+    out.push_str(&format!("pub struct {}(u32);\n\n", type_name));
+    out.push_str(&format!("impl {} {{\n", type_name));
+    out.push_str("    #[inline(always)]\n    pub fn bits(self) -> u32 {\n        self.0\n    }\n\n");
+    for f in fields {
+        let width = f.msb - f.lsb + 1;
+        let getter = f.name.to_lowercase();
+        if width == 1 {
+            out.push_str(&format!(
+                "    #[inline(always)]\n    pub fn {}(self) -> bool {{\n        (self.0 >> {}) & 0x1 != 0\n    }}\n\n",
+                getter, f.lsb));
+            out.push_str(&format!(
+                "    #[inline(always)]\n    pub fn set_{}(mut self, value: bool) -> Self {{\n        if value {{\n            self.0 |= 1 << {};\n        }} else {{\n            self.0 &= !(1u32 << {});\n        }}\n        self\n    }}\n\n",
+                getter, f.lsb, f.lsb));
+        } else {
+            let mask: u64 = (1u64 << width) - 1;
+            out.push_str(&format!(
+                "    #[inline(always)]\n    pub fn {}(self) -> u32 {{\n        (self.0 >> {}) & 0x{:x}\n    }}\n\n",
+                getter, f.lsb, mask));
+            out.push_str(&format!(
+                "    #[inline(always)]\n    pub fn set_{}(mut self, value: u32) -> Self {{\n        self.0 = (self.0 & !(0x{:x} << {})) | ((value & 0x{:x}) << {});\n        self\n    }}\n\n",
+                getter, mask, f.lsb, mask, f.lsb));
+        }
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+// EDC describes 8-, 16- and 32-bit SFRs; the accessor must read/write/mask
+// at the register's actual width, not always a full 32-bit word, or it
+// would perform an out-of-bounds volatile access on narrower registers
+fn int_type(width: u32) -> &'static str {
+    match width {
+        8 => "u8",
+        16 => "u16",
+        _ => "u32",
+    }
+}
+
+fn gen_register_accessor(
+    accessor_name: &str,
+    type_name: &str,
+    offset: u32,
+    width: u32,
+    set_off: Option<u32>,
+    clr_off: Option<u32>,
+    inv_off: Option<u32>,
+) -> String {
+    let ity = int_type(width);
+    let mut out = String::new();
+    out.push_str(&format!("pub struct {} {{\n    base: u32,\n}}\n\n", accessor_name));
+    out.push_str(&format!("impl {} {{\n", accessor_name));
+    out.push_str(&format!(
+        "    #[inline(always)]\n    pub fn read(&self) -> {} {{\n        {}(unsafe {{ core::ptr::read_volatile((self.base + 0x{:x}) as *const {}) }} as u32)\n    }}\n\n",
+        type_name, type_name, offset, ity));
+    out.push_str(&format!(
+        "    #[inline(always)]\n    pub fn write(&self, value: {}) {{\n        unsafe {{ core::ptr::write_volatile((self.base + 0x{:x}) as *mut {}, value.bits() as {}) }}\n    }}\n\n",
+        type_name, offset, ity, ity));
+    out.push_str(&format!(
+        "    #[inline(always)]\n    pub fn modify<F: FnOnce({}) -> {}>(&self, f: F) {{\n        self.write(f(self.read()));\n    }}\n\n",
+        type_name, type_name));
+    if let Some(o) = set_off {
+        out.push_str(&format!(
+            "    /// Atomically sets the bits in `mask` by writing the `SET` alias register.\n    #[inline(always)]\n    pub fn set_bits(&self, mask: {}) {{\n        unsafe {{ core::ptr::write_volatile((self.base + 0x{:x}) as *mut {}, mask) }}\n    }}\n\n",
+            ity, o, ity));
+    }
+    if let Some(o) = clr_off {
+        out.push_str(&format!(
+            "    /// Atomically clears the bits in `mask` by writing the `CLR` alias register.\n    #[inline(always)]\n    pub fn clear_bits(&self, mask: {}) {{\n        unsafe {{ core::ptr::write_volatile((self.base + 0x{:x}) as *mut {}, mask) }}\n    }}\n\n",
+            ity, o, ity));
+    }
+    if let Some(o) = inv_off {
+        out.push_str(&format!(
+            "    /// Atomically toggles the bits in `mask` by writing the `INV` alias register.\n    #[inline(always)]\n    pub fn toggle_bits(&self, mask: {}) {{\n        unsafe {{ core::ptr::write_volatile((self.base + 0x{:x}) as *mut {}, mask) }}\n    }}\n\n",
+            ity, o, ity));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+fn generate_peripheral(peri_e: &Element) -> String {
+    let name = child_text(peri_e, "name");
+    let description = child_text(peri_e, "description");
+    let base_addr = parse_hex_or_dec(child_text(peri_e, "baseAddress"));
+    let registers_e = peri_e
+        .get_child("registers")
+        .expect("peripheral element without <registers>");
+
+    let mut out = String::new();
+    let mut extra = String::new();
+
+    out.push_str(&format!("/// {}\n", description));
+    out.push_str(&format!("pub struct {};\n\n", name));
+    out.push_str(&format!("impl {} {{\n", name));
+    out.push_str(&format!("    const BASE: u32 = 0x{:08x};\n\n", base_addr));
+
+    let mut regs = registers_e
+        .children
+        .iter()
+        .filter(|e| e.name == "register")
+        .peekable();
+
+    while let Some(reg_e) = regs.next() {
+        let reg_name = child_text(reg_e, "name");
+        let offset = parse_hex_or_dec(child_text(reg_e, "addressOffset"));
+        let width = parse_hex_or_dec(child_text(reg_e, "size"));
+        let fields = parse_fields(reg_e);
+
+        // the CLR/SET/INV sibling registers directly follow their base
+        // register and are folded into set_bits()/clear_bits()/toggle_bits()
+        // helpers instead of becoming accessors of their own
+        let mut set_off = None;
+        let mut clr_off = None;
+        let mut inv_off = None;
+        while let Some(next_e) = regs.peek() {
+            let next_name = child_text(next_e, "name");
+            if next_name == format!("{}SET", reg_name) {
+                set_off = Some(parse_hex_or_dec(child_text(next_e, "addressOffset")));
+            } else if next_name == format!("{}CLR", reg_name) {
+                clr_off = Some(parse_hex_or_dec(child_text(next_e, "addressOffset")));
+            } else if next_name == format!("{}INV", reg_name) {
+                inv_off = Some(parse_hex_or_dec(child_text(next_e, "addressOffset")));
+            } else {
+                break;
+            }
+            regs.next();
+        }
+
+        let type_name = format!("{}Reg", reg_name);
+        let accessor_name = format!("{}Access", reg_name);
+        let method_name = reg_name.to_lowercase();
+
+        extra.push_str(&gen_register_newtype(&type_name, &fields));
+        extra.push_str(&gen_register_accessor(
+            &accessor_name,
+            &type_name,
+            offset,
+            width,
+            set_off,
+            clr_off,
+            inv_off,
+        ));
+
+        out.push_str(&format!(
+            "    #[inline(always)]\n    pub fn {}(&self) -> {} {{\n        {} {{ base: Self::BASE }}\n    }}\n\n",
+            method_name, accessor_name, accessor_name
+        ));
+    }
+    out.push_str("}\n\n");
+    out.push_str(&extra);
+    out
+}
+
+/// Generates a `no_std` Rust peripheral-access module from the peripheral
+/// element tree that `analyze_periph` would otherwise serialize as SVD.
+pub fn generate_pac(device_name: &str, periph_out: &Element) -> String {
+    let mut out = String::new();
+    out.push_str("// Generated by edc2svd -f rust; do not edit by hand.\n");
+    out.push_str(&format!("//! Peripheral access crate for {}\n", device_name));
+    out.push_str("#![no_std]\n\n");
+    for peri_e in periph_out
+        .children
+        .iter()
+        .filter(|e| e.name == "peripheral" && e.get_child("registers").is_some())
+    {
+        out.push_str(&generate_peripheral(peri_e));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_elem(name: &str, bit_range: &str) -> Element {
+        let mut field_e = Element::new("field");
+        let mut name_e = Element::new("name");
+        name_e.text = Some(name.to_string());
+        let mut range_e = Element::new("bitRange");
+        range_e.text = Some(bit_range.to_string());
+        field_e.children.push(name_e);
+        field_e.children.push(range_e);
+        field_e
+    }
+
+    #[test]
+    fn parse_fields_reads_msb_lsb_from_bit_range() {
+        let mut fields_e = Element::new("fields");
+        fields_e.children.push(field_elem("MODE", "[3:0]"));
+        let mut reg_e = Element::new("register");
+        reg_e.children.push(fields_e);
+
+        let fields = parse_fields(&reg_e);
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].name, "MODE");
+        assert_eq!(fields[0].lsb, 0);
+        assert_eq!(fields[0].msb, 3);
+    }
+
+    #[test]
+    fn parse_fields_empty_without_fields_element() {
+        let reg_e = Element::new("register");
+        assert!(parse_fields(&reg_e).is_empty());
+    }
+
+    #[test]
+    fn int_type_maps_known_widths() {
+        assert_eq!(int_type(8), "u8");
+        assert_eq!(int_type(16), "u16");
+        assert_eq!(int_type(32), "u32");
+    }
+
+    #[test]
+    fn gen_register_newtype_emits_bool_accessor_for_single_bit_field() {
+        let fields = vec![Field { name: "EN".to_string(), lsb: 0, msb: 0 }];
+        let out = gen_register_newtype("CTRLReg", &fields);
+        assert!(out.contains("pub fn en(self) -> bool"));
+        assert!(out.contains("pub fn set_en(mut self, value: bool) -> Self"));
+    }
+
+    #[test]
+    fn gen_register_newtype_emits_u32_accessor_for_multibit_field() {
+        let fields = vec![Field { name: "MODE".to_string(), lsb: 0, msb: 3 }];
+        let out = gen_register_newtype("CTRLReg", &fields);
+        assert!(out.contains("pub fn mode(self) -> u32"));
+        assert!(out.contains("0xf"));
+    }
+}